@@ -2,6 +2,11 @@ use ffi;
 use libc::{c_uint, c_int, c_void};
 use num::complex::Complex64;
 
+#[cfg(feature = "std")]
+use std::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 use plan::RawPlan;
 
 /// How much effort FFTW should put into computing the best strategy
@@ -35,17 +40,20 @@ pub struct Planner {
     rigor: Rigor,
     wisdom_restriction: bool,
     direction: Direction,
+    nthreads: uint,
 }
 
 impl Planner {
     /// Construct a new planner with default values.
     ///
-    /// This defaults to a forward transform with estimate rigor.
+    /// This defaults to a forward transform with estimate rigor,
+    /// planned single-threaded.
     pub fn new() -> Planner {
         Planner {
             rigor: Estimate,
             wisdom_restriction: false,
             direction: Forward,
+            nthreads: 1,
         }
     }
 
@@ -56,6 +64,10 @@ impl Planner {
     }
     /// Set whether the planner should only be successfully created if
     /// there exists wisdom created with at least the rigor level set.
+    ///
+    /// With this set, `PlanMem::plan` returns `Err` instead of
+    /// measuring a new plan when no matching wisdom has been loaded;
+    /// see the `wisdom` module for how to load it.
     pub fn wisdom_restriction(&mut self, wisdom_only: bool) -> &mut Planner {
         self.wisdom_restriction = wisdom_only;
         self
@@ -67,6 +79,16 @@ impl Planner {
         self
     }
 
+    /// Set the number of threads FFTW should use to plan and execute
+    /// the transform.
+    ///
+    /// Requires `threads::init_threads` to have been called once
+    /// beforehand. Defaults to 1 (single-threaded).
+    pub fn nthreads(&mut self, n: uint) -> &mut Planner {
+        self.nthreads = n;
+        self
+    }
+
     fn flags(&self) -> c_uint {
         self.rigor.flags() | if self.wisdom_restriction {
             ffi::FFTW_WISDOM_ONLY
@@ -91,14 +113,19 @@ impl Planner {
         assert!(in_.len() <= 0x7F_FF_FF_FF);
         assert!(in_.len() <= out.len());
         let dims = Detailed(vec![Dim { n: in_.len(), in_stride: 1, out_stride: 1 }]);
+        let n = in_.len();
         PlanMem {
             plan: *self,
             in_: in_,
             out: Some(out),
-            planner: c2c,
+            planner: Complex(c2c),
+            r2r_kinds: None,
+            kind: C2C,
 
             dims: dims,
             how_many: Contiguous(vec![1]),
+            in_total: n,
+            out_total: n,
         }
     }
     pub fn c2r<I, O>(&self, in_: I, out: O) -> PlanMem<I, O>
@@ -106,16 +133,21 @@ impl Planner {
     {
         assert!(in_.len() <= 0x7F_FF_FF_FF);
         assert!(in_.len() <= out.len() / 2 + 1);
-        let dims = Detailed(vec![Dim { n: 2 * (in_.len() - 1),
-                                       in_stride: 1, out_stride: 1 }]);
+        let n = 2 * (in_.len() - 1);
+        let dims = Detailed(vec![Dim { n: n, in_stride: 1, out_stride: 1 }]);
+        let in_total = in_.len();
         PlanMem {
             plan: *self,
             in_: in_,
             out: Some(out),
-            planner: c2r,
+            planner: Complex(c2r),
+            r2r_kinds: None,
+            kind: C2R,
 
             dims: dims,
             how_many: Contiguous(vec![1]),
+            in_total: in_total,
+            out_total: n,
         }
     }
     pub fn r2c<I, O>(&self, in_: I, out: O) -> PlanMem<I, O>
@@ -123,32 +155,49 @@ impl Planner {
     {
         assert!(in_.len() <= 0x7F_FF_FF_FF);
         assert!(in_.len() / 2 + 1 <= out.len());
-        let dims = Detailed(vec![Dim { n: in_.len(), in_stride: 1, out_stride: 1 }]);
+        let n = in_.len();
+        let dims = Detailed(vec![Dim { n: n, in_stride: 1, out_stride: 1 }]);
         PlanMem {
             plan: *self,
             in_: in_,
             out: Some(out),
-            planner: r2c,
+            planner: Complex(r2c),
+            r2r_kinds: None,
+            kind: R2C,
 
             dims: dims,
             how_many: Contiguous(vec![1]),
+            in_total: n,
+            out_total: n / 2 + 1,
         }
     }
-    #[cfg(r2r_is_hard)]
-    pub fn r2r<I, O>(&self, in_: I, out: O) -> PlanMem<I, O>
+    /// Plan a real-to-real transform (a DCT, a DST, or a real DFT in
+    /// halfcomplex format), one `R2RKind` per dimension.
+    ///
+    /// `kinds` must have exactly one entry for a plan built with this
+    /// method; calling `PlanMem::dimensions` to go to rank `N`
+    /// requires `N` kinds, set via `PlanMem::r2r_kinds`.
+    pub fn r2r<I, O>(&self, kinds: Vec<R2RKind>, in_: I, out: O) -> PlanMem<I, O>
         where I: DerefMut<[f64]>, O: DerefMut<[f64]>
     {
         assert!(in_.len() <= 0x7F_FF_FF_FF);
         assert!(in_.len() <= out.len());
-        let dims = Detailed(vec![Dim { n: in_.len(), in_stride: 1, out_stride: 1 }]);
+        assert!(kinds.len() == 1,
+                "Planner::r2r: expected 1 kind for a 1-D transform, found {}", kinds.len());
+        let n = in_.len();
+        let dims = Detailed(vec![Dim { n: n, in_stride: 1, out_stride: 1 }]);
         PlanMem {
             plan: *self,
             in_: in_,
             out: Some(out),
-            planner: r2r,
+            planner: RealToReal(r2r),
+            r2r_kinds: Some(kinds),
+            kind: C2C,
 
             dims: dims,
             how_many: Contiguous(vec![1]),
+            in_total: n,
+            out_total: n,
         }
     }
 }
@@ -162,15 +211,20 @@ impl InPlacePlanner {
         where I: DerefMut<[Complex64]>
     {
         assert!(in_.len() <= 0x7F_FF_FF_FF);
-        let dims = Detailed(vec![Dim { n: in_.len(), in_stride: 1, out_stride: 1 }]);
+        let n = in_.len();
+        let dims = Detailed(vec![Dim { n: n, in_stride: 1, out_stride: 1 }]);
         PlanMem {
             plan: self.plan,
             in_: in_,
             out: None,
-            planner: c2c,
+            planner: Complex(c2c),
+            r2r_kinds: None,
+            kind: C2C,
 
             dims: dims,
             how_many: Contiguous(vec![1]),
+            in_total: n,
+            out_total: n,
         }
     }
 }
@@ -181,6 +235,20 @@ type GuruPlanner =
               in_: *mut c_void, out: *mut c_void,
               sign: c_int, flags: c_uint) -> ffi::fftw_plan;
 
+type GuruR2RPlanner =
+    unsafe fn(rank: c_int, dims: *const ffi::fftw_iodim64,
+              howmany_rank: c_int, howmany_dims: *const ffi::fftw_iodim64,
+              in_: *mut f64, out: *mut f64,
+              kind: *const ffi::fftw_r2r_kind, flags: c_uint) -> ffi::fftw_plan;
+
+/// The two calling conventions the guru64 planners come in: the
+/// `sign`-taking complex/half-complex family, and the `kind`-array-taking
+/// real-to-real family.
+enum GuruFn {
+    Complex(GuruPlanner),
+    RealToReal(GuruR2RPlanner),
+}
+
 unsafe fn c2c(rank: c_int, dims: *const ffi::fftw_iodim64,
               howmany_rank: c_int, howmany_dims: *const ffi::fftw_iodim64,
               in_: *mut c_void, out: *mut c_void,
@@ -209,14 +277,51 @@ unsafe fn c2r(rank: c_int, dims: *const ffi::fftw_iodim64,
                                 in_ as *mut _, out as *mut _,
                                 flags)
 }
-#[cfg(r2r_is_hard)]
-unsafe fn r2r(n: c_int, in_: *mut c_void, out: *mut c_void,
-              sign: c_int, flags: c_uint) -> ffi::fftw_plan {
-    ffi::fftw_plan_r2r_1d(n,
-                          in_ as *mut f64, out as *mut f64,
-                          sign, flags)
+unsafe fn r2r(rank: c_int, dims: *const ffi::fftw_iodim64,
+              howmany_rank: c_int, howmany_dims: *const ffi::fftw_iodim64,
+              in_: *mut f64, out: *mut f64,
+              kind: *const ffi::fftw_r2r_kind, flags: c_uint) -> ffi::fftw_plan {
+    ffi::fftw_plan_guru64_r2r(rank, dims,
+                              howmany_rank, howmany_dims,
+                              in_, out,
+                              kind, flags)
 }
 
+/// The kind of real-to-real transform to perform along a single
+/// dimension: a real DFT to (`R2HC`) or from (`HC2R`) FFTW's
+/// halfcomplex format, the four DCT types, and the four DST types. The
+/// discrete Hartley transform (`FFTW_DHT`) is not offered. See the FFTW
+/// manual's "Real-to-Real Transform Kinds" section for the precise
+/// mathematical definition of each.
+#[deriving(Copy, Clone)]
+pub enum R2RKind {
+    R2HC,
+    HC2R,
+    REDFT00,
+    REDFT01,
+    REDFT10,
+    REDFT11,
+    RODFT00,
+    RODFT01,
+    RODFT10,
+    RODFT11,
+}
+impl R2RKind {
+    fn kind(self) -> ffi::fftw_r2r_kind {
+        match self {
+            R2HC => ffi::FFTW_R2HC,
+            HC2R => ffi::FFTW_HC2R,
+            REDFT00 => ffi::FFTW_REDFT00,
+            REDFT01 => ffi::FFTW_REDFT01,
+            REDFT10 => ffi::FFTW_REDFT10,
+            REDFT11 => ffi::FFTW_REDFT11,
+            RODFT00 => ffi::FFTW_RODFT00,
+            RODFT01 => ffi::FFTW_RODFT01,
+            RODFT10 => ffi::FFTW_RODFT10,
+            RODFT11 => ffi::FFTW_RODFT11,
+        }
+    }
+}
 
 #[repr(C)]
 pub struct Dim {
@@ -230,26 +335,119 @@ enum Dims {
     Detailed(Vec<Dim>),
 }
 
+/// Which side of a transform (if any) stores a halved last dimension,
+/// i.e. which side is the "packed" complex array of a real transform.
+enum Kind {
+    C2C,
+    R2C,
+    C2R,
+}
+
+/// Compute row-major strides for a set of dimension counts, given the
+/// stride of a single element of the innermost dimension.
+///
+/// `strides[rank - 1] == base`, and `strides[i] == strides[i + 1] *
+/// counts[i + 1]` for every other `i`.
+fn row_major_strides(counts: &[uint], base: uint) -> Vec<uint> {
+    let rank = counts.len();
+    let mut strides = Vec::from_elem(rank, 0u);
+    if rank == 0 {
+        return strides;
+    }
+    strides[rank - 1] = base;
+    let mut i = rank - 1;
+    while i > 0 {
+        i -= 1;
+        strides[i] = strides[i + 1] * counts[i + 1];
+    }
+    strides
+}
+
+/// Turn a `Dims` (as used for either the transform's own dimensions or
+/// its `how_many` loop) into the `Dim`s the guru planner wants,
+/// treating `Contiguous` as a plain row-major loop starting at the
+/// given per-side base strides.
+fn to_dims(d: &Dims, in_base: uint, out_base: uint) -> Vec<Dim> {
+    match *d {
+        Detailed(ref v) => {
+            v.iter().map(|d| Dim { n: d.n, in_stride: d.in_stride, out_stride: d.out_stride }).collect()
+        }
+        Contiguous(ref counts) => {
+            let in_strides = row_major_strides(counts.as_slice(), in_base);
+            let out_strides = row_major_strides(counts.as_slice(), out_base);
+            range(0, counts.len()).map(|i| {
+                Dim { n: counts[i], in_stride: in_strides[i], out_stride: out_strides[i] }
+            }).collect()
+        }
+    }
+}
+
 pub struct PlanMem<I, O> {
     plan: Planner,
     dims: Dims,
     how_many: Dims,
     in_: I,
     out: Option<O>,
-    planner: GuruPlanner
+    planner: GuruFn,
+    // one `R2RKind` per dimension; `None` unless `planner` is `RealToReal`.
+    r2r_kinds: Option<Vec<R2RKind>>,
+    kind: Kind,
+    // the number of elements a single (non-batched) transform reads
+    // from `in_`/writes to `out`; used as the stride between
+    // repetitions when `how_many` describes a batch.
+    in_total: uint,
+    out_total: uint,
 }
 
 impl<X, Y, I: DerefMut<[X]>, O: DerefMut<[Y]>> PlanMem<I, O> {
+    /// Turn this into an `N`-dimensional transform over `dims`
+    /// (outermost dimension first, innermost last), replacing
+    /// whatever dimensions were set before.
+    ///
+    /// Strides are computed assuming the usual FFTW row-major,
+    /// contiguous layout; for `r2c`/`c2r` plans the packed dimension
+    /// (the last one) is correctly halved on the complex side only.
     pub fn dimensions(mut self, dims: Vec<uint>) -> PlanMem<I, O> {
-        unimplemented!()
-        self.dims = Contiguous(dims);
+        assert!(dims.len() >= 1, "PlanMem::dimensions: need at least one dimension");
+        let last = dims.len() - 1;
+
+        let mut in_counts = dims.clone();
+        let mut out_counts = dims.clone();
+        match self.kind {
+            C2C => {}
+            R2C => out_counts[last] = dims[last] / 2 + 1,
+            C2R => in_counts[last] = dims[last] / 2 + 1,
+        }
+
+        let in_strides = row_major_strides(in_counts.as_slice(), 1);
+        let out_strides = row_major_strides(out_counts.as_slice(), 1);
+
+        self.dims = Detailed(range(0, dims.len()).map(|i| {
+            Dim { n: dims[i], in_stride: in_strides[i], out_stride: out_strides[i] }
+        }).collect());
+        self.in_total = in_counts.iter().fold(1u, |a, &b| a * b);
+        self.out_total = out_counts.iter().fold(1u, |a, &b| a * b);
         self
     }
+    /// Repeat the transform `number` times over consecutive,
+    /// contiguous blocks of `in_`/`out`, producing `number` independent
+    /// transforms from a single plan.
     pub fn multiples(mut self, number: uint) -> PlanMem<I, O> {
-        unimplemented!()
         self.how_many = Contiguous(vec![number]);
         self
     }
+    /// Set the per-dimension `R2RKind`s for a real-to-real plan built
+    /// with `Planner::r2r`.
+    ///
+    /// Must be called with one kind per dimension after any call to
+    /// `dimensions`, since raising the rank invalidates the single
+    /// default kind `Planner::r2r` set up for a 1-D transform.
+    pub fn r2r_kinds(mut self, kinds: Vec<R2RKind>) -> PlanMem<I, O> {
+        assert!(self.r2r_kinds.is_some(),
+                "PlanMem::r2r_kinds: only valid for a plan built with Planner::r2r");
+        self.r2r_kinds = Some(kinds);
+        self
+    }
     pub fn plan(mut self) -> Result<Planned<I, O>, PlanMem<I, O>> {
         let plan;
         {
@@ -258,21 +456,86 @@ impl<X, Y, I: DerefMut<[X]>, O: DerefMut<[Y]>> PlanMem<I, O> {
                 None => in_ptr,
                 Some(ref mut o) => o.as_mut_ptr() as *mut c_void,
             };
-            let dims = match self.dims {
-                Contiguous(_) => unimplemented!(),
-                Detailed(ref v) => v.as_slice()
+
+            let dims = to_dims(&self.dims, 1, 1);
+            assert!(dims.len() >= 1);
+            let how_many = to_dims(&self.how_many, self.in_total, self.out_total);
+            let repeats = how_many.iter().fold(1u, |a, d| a * d.n);
+
+            assert!(self.in_.len() >= self.in_total * repeats,
+                    "PlanMem::plan: `in_` has length {}, but the requested dimensions \
+                     and batch size require at least {}", self.in_.len(), self.in_total * repeats);
+            if let Some(ref o) = self.out {
+                assert!(o.len() >= self.out_total * repeats,
+                        "PlanMem::plan: `out` has length {}, but the requested dimensions \
+                         and batch size require at least {}", o.len(), self.out_total * repeats);
+            }
+
+            // `fftw_plan_with_nthreads` sets a global consulted by every
+            // plan created after it, so, when a caller has actually
+            // opted into threading via `Planner::nthreads`, it must run
+            // in the same lock region as the guru planner call below
+            // (which `lock::run` provides) to avoid racing with
+            // another thread's plan creation, and it is set back to 1
+            // before that region ends so that later plans with the
+            // default count stay single-threaded. `nthreads` defaults to 1
+            // and FFTW only expects `fftw_plan_with_nthreads` to be
+            // called once `threads::init_threads()` has been run (it
+            // lives in FFTW's separate threads/OpenMP library), so
+            // leave the global alone entirely on the default,
+            // single-threaded path.
+            let nthreads = self.plan.nthreads as c_int;
+
+            let raw = match self.planner {
+                Complex(f) => ::lock::run(|| unsafe {
+                    if nthreads != 1 {
+                        ffi::fftw_plan_with_nthreads(nthreads);
+                    }
+                    let p = f(dims.len() as c_int, dims.as_ptr() as *const ffi::fftw_iodim64,
+                              how_many.len() as c_int, how_many.as_ptr() as *const ffi::fftw_iodim64,
+                              in_ptr,
+                              out_ptr,
+                              self.plan.dir(),
+                              self.plan.flags());
+                    if nthreads != 1 {
+                        ffi::fftw_plan_with_nthreads(1);
+                    }
+                    p
+                }),
+                RealToReal(f) => {
+                    let kinds = self.r2r_kinds.as_ref()
+                        .expect("PlanMem::plan: real-to-real plan is missing its R2RKinds");
+                    assert!(kinds.len() == dims.len(),
+                            "PlanMem::plan: have {} dimensions but {} r2r kinds",
+                            dims.len(), kinds.len());
+                    let kind_vals: Vec<ffi::fftw_r2r_kind> =
+                        kinds.iter().map(|&k| k.kind()).collect();
+
+                    ::lock::run(|| unsafe {
+                        if nthreads != 1 {
+                            ffi::fftw_plan_with_nthreads(nthreads);
+                        }
+                        let p = f(dims.len() as c_int, dims.as_ptr() as *const ffi::fftw_iodim64,
+                                  how_many.len() as c_int, how_many.as_ptr() as *const ffi::fftw_iodim64,
+                                  in_ptr as *mut f64,
+                                  out_ptr as *mut f64,
+                                  kind_vals.as_ptr(),
+                                  self.plan.flags());
+                        if nthreads != 1 {
+                            ffi::fftw_plan_with_nthreads(1);
+                        }
+                        p
+                    })
+                }
+            };
+            // FFTW returns a NULL plan when it cannot create one (e.g. no
+            // matching wisdom under `wisdom_restriction`); that becomes
+            // `Err` below rather than a panic in `RawPlan::new`.
+            plan = if raw.is_null() {
+                None
+            } else {
+                Some(unsafe { RawPlan::new_unchecked(raw) })
             };
-            assert!(dims.len() == 1);
-
-            plan = RawPlan::new(|| unsafe {
-                (self.planner)(
-                    dims.len() as c_int, dims.as_ptr() as *const ffi::fftw_iodim64,
-                    0, [].as_ptr(),
-                    in_ptr,
-                    out_ptr,
-                    self.plan.dir(),
-                    self.plan.flags())
-            });
         }
         match plan {
             None => Err(self),
@@ -324,4 +587,34 @@ mod tests {
             assert_eq!(mem::transmute::<_, T>(d), mem::transmute::<_, T>(f));
         }
     }
+
+    #[test]
+    fn row_major_strides_3d() {
+        let strides = super::row_major_strides(&[2u, 3, 5], 1);
+        assert_eq!(strides, vec![15u, 5, 1]);
+    }
+
+    #[test]
+    fn row_major_strides_packed_last_axis() {
+        // as used for the complex side of an r2c transform: the last
+        // axis is replaced by its packed (n/2+1) count before the
+        // strides of the outer axes are derived from it.
+        let dims = vec![2u, 3, 8];
+        let mut packed = dims.clone();
+        let last = packed.len() - 1;
+        packed[last] = dims[last] / 2 + 1;
+
+        let strides = super::row_major_strides(packed.as_slice(), 1);
+        assert_eq!(strides, vec![15u, 5, 1]);
+    }
+
+    #[test]
+    fn r2r_plan_builds() {
+        // exercises the one code path that actually assembles the
+        // `fftw_r2r_kind` array passed to `fftw_plan_guru64_r2r`.
+        let in_ = Vec::from_elem(8, 0f64);
+        let out = Vec::from_elem(8, 0f64);
+        let mem = super::Planner::new().r2r(vec![super::R2RKind::R2HC], in_, out);
+        assert!(mem.plan().is_ok());
+    }
 }
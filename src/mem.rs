@@ -0,0 +1,129 @@
+//! Everything in this module is `core`+`alloc` only: `FFTWVec`'s
+//! storage comes from `fftw_malloc`/`fftw_free` rather than the global
+//! Rust allocator, both for the SIMD-friendly alignment that gives
+//! `Rigor::Measure`-and-above plans and so it works without `std` at
+//! all. `std`-only conveniences (e.g. file-based wisdom) live
+//! elsewhere, behind the `std` feature.
+
+use core::mem;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::slice;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use libc::c_void;
+
+use ffi;
+
+/// A type that can hand out its backing memory as a plain slice.
+///
+/// Implementors keep their memory behind `MaybeUninit<T>` until it is
+/// known to be initialised (analogous to an `Option` that tracks
+/// "not yet set"), and only expose a bare `&mut [T]` through
+/// `storage_slice`/`assume_init`, which callers must go through
+/// instead of reaching at the raw storage directly.
+pub trait BackingStorage<T> {
+    /// The backing memory, with no initialisation guarantee.
+    fn raw_slice(&mut self) -> &mut [MaybeUninit<T>];
+
+    /// Treat the backing memory as initialised.
+    ///
+    /// # Safety
+    /// Every element of the storage must already have been written.
+    unsafe fn assume_init(&mut self) -> &mut [T] {
+        let raw = self.raw_slice();
+        slice::from_raw_parts_mut(raw.as_mut_ptr() as *mut T, raw.len())
+    }
+
+    /// The initialised backing memory.
+    ///
+    /// Implementors that cannot vouch for their memory being
+    /// initialised (e.g. a freshly allocated `FFTWVec`) must arrange
+    /// to initialise it themselves before it is ever reachable through
+    /// this method.
+    fn storage_slice(&mut self) -> &mut [T] {
+        unsafe { self.assume_init() }
+    }
+}
+
+impl<'a, T> BackingStorage<T> for &'a mut [T] {
+    fn raw_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr() as *mut MaybeUninit<T>, self.len()) }
+    }
+}
+
+impl<T> BackingStorage<T> for Vec<T> {
+    fn raw_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr() as *mut MaybeUninit<T>, self.len()) }
+    }
+}
+
+/// A buffer allocated through `fftw_malloc`, so that it is suitably
+/// aligned for FFTW's SIMD codelets.
+///
+/// Tracks, at runtime, whether its contents have actually been
+/// initialised, since `uninit` is the one way to get an `FFTWVec`
+/// without FFTW (or Rust) having written anything into it yet.
+pub struct FFTWVec<T> {
+    ptr: *mut MaybeUninit<T>,
+    len: uint,
+    initialized: bool,
+}
+
+impl<T> FFTWVec<T> {
+    /// Allocate space for `n` elements of `T` via `fftw_malloc`,
+    /// without initialising them.
+    ///
+    /// The result does not allow `storage_slice` until `zero_fill` has
+    /// run; calling it first panics rather than handing out unwritten
+    /// memory. `assume_init` does not check this: after writing every
+    /// element some other way (e.g. through `raw_slice`), it is the way
+    /// to reach them as plain `T`s.
+    ///
+    /// # Safety
+    /// `fftw_free` is run on drop no matter what, so `n` must describe
+    /// an allocation `fftw_malloc` is able to satisfy and later free.
+    pub unsafe fn uninit(n: uint) -> FFTWVec<T> {
+        let bytes = n * mem::size_of::<T>();
+        let ptr = ffi::fftw_malloc(bytes as ::libc::size_t) as *mut MaybeUninit<T>;
+        if ptr.is_null() {
+            panic!("FFTWVec::uninit: fftw_malloc returned NULL");
+        }
+        FFTWVec { ptr: ptr, len: n, initialized: false }
+    }
+
+    /// Zero every byte of the buffer, initialising it for `T`s whose
+    /// all-zero bit pattern is a valid value (as `f64` and
+    /// `Complex64` both are), and mark the buffer as initialised.
+    pub fn zero_fill(&mut self) {
+        unsafe {
+            ptr::write_bytes(self.ptr as *mut u8, 0, self.len * mem::size_of::<T>());
+        }
+        self.initialized = true;
+    }
+}
+
+impl<T> BackingStorage<T> for FFTWVec<T> {
+    fn raw_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    fn storage_slice(&mut self) -> &mut [T] {
+        if !self.initialized {
+            panic!("FFTWVec::storage_slice: called on storage from `uninit` \
+                    that has not been initialised (e.g. via `zero_fill`) yet");
+        }
+        unsafe { self.assume_init() }
+    }
+}
+
+#[unsafe_destructor]
+impl<T> Drop for FFTWVec<T> {
+    fn drop(&mut self) {
+        unsafe { ffi::fftw_free(self.ptr as *mut c_void) }
+    }
+}
@@ -100,7 +100,9 @@ impl<In: BackingStorage<f64>, Out: BackingStorage<Complex64>> Plan<In, Out> {
 impl Plan<FFTWVec<f64>, FFTWVec<Complex64>> {
     pub fn r2c_1d(n: uint) -> Plan<FFTWVec<f64>, FFTWVec<Complex64>> {
         unsafe {
-            let (in_, out) = (FFTWVec::uninit(n), FFTWVec::uninit(n / 2 + 1));
+            let (mut in_, mut out) = (FFTWVec::uninit(n), FFTWVec::uninit(n / 2 + 1));
+            in_.zero_fill();
+            out.zero_fill();
 
             Plan::r2c_1d_prealloc(in_, out)
         }
@@ -130,7 +132,9 @@ impl<In: BackingStorage<Complex64>, Out: BackingStorage<f64>> Plan<In, Out> {
 impl Plan<FFTWVec<Complex64>, FFTWVec<f64>> {
     pub fn c2r_1d(n: uint) -> Plan<FFTWVec<Complex64>, FFTWVec<f64>> {
         unsafe {
-            let (in_, out) = (FFTWVec::uninit(n / 2 + 1), FFTWVec::uninit(n));
+            let (mut in_, mut out) = (FFTWVec::uninit(n / 2 + 1), FFTWVec::uninit(n));
+            in_.zero_fill();
+            out.zero_fill();
 
             Plan::c2r_1d_prealloc(in_, out)
         }
@@ -164,7 +168,9 @@ impl<In: BackingStorage<Complex64>, Out: BackingStorage<Complex64>> Plan<In, Out
 impl Plan<FFTWVec<Complex64>, FFTWVec<Complex64>> {
     pub fn c2c_1d(n: uint) -> Plan<FFTWVec<Complex64>, FFTWVec<Complex64>> {
         unsafe {
-            let (in_, out) = (FFTWVec::uninit(n), FFTWVec::uninit(n));
+            let (mut in_, mut out) = (FFTWVec::uninit(n), FFTWVec::uninit(n));
+            in_.zero_fill();
+            out.zero_fill();
 
             Plan::c2c_1d_prealloc(in_, out)
         }
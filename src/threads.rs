@@ -0,0 +1,22 @@
+use ffi;
+use lock;
+
+/// Enable FFTW's threaded backend.
+///
+/// This must be called once, before building any plan that uses
+/// `Planner::nthreads`. It wraps `fftw_init_threads` and runs under
+/// `lock::run` since it touches FFTW's global state.
+///
+/// Returns `false` if FFTW could not initialise its threading support,
+/// in which case plans built with `nthreads` will simply run
+/// single-threaded.
+pub fn init_threads() -> bool {
+    lock::run(|| unsafe { ffi::fftw_init_threads() != 0 })
+}
+
+/// Release the resources allocated by `init_threads`.
+///
+/// Do not build any further multi-threaded plans after calling this.
+pub fn cleanup_threads() {
+    lock::run(|| unsafe { ffi::fftw_cleanup_threads() })
+}
@@ -0,0 +1,87 @@
+// File- and string-based wisdom both need `std::path`/`std::c_str`, so
+// this whole module is `std`-only; it is not reachable when the crate
+// is built with `std` disabled for `no_std` targets.
+#![cfg(feature = "std")]
+
+//! Save and restore FFTW's accumulated "wisdom".
+//!
+//! Building a plan with `Rigor::Patient` or `Rigor::Exhaustive` can
+//! take a long time, because FFTW actually measures a number of
+//! candidate strategies before picking the fastest one. That
+//! measurement is what FFTW calls wisdom, and it can be exported after
+//! planning and re-imported on a later run (even in a different
+//! process) so the cost is only paid once.
+//!
+//! A typical flow is to `import_from_file` at startup, build plans
+//! with a high `Rigor`, then `export_to_file` at shutdown. Combined
+//! with `Planner::wisdom_restriction(true)`, plan creation then fails
+//! with `Err` (via `PlanMem::plan`'s `Result`) instead of silently
+//! re-measuring whenever no matching wisdom is present.
+//!
+//! The wisdom store is process-global, mutable state that FFTW does
+//! not guard itself, so every function here runs under `lock::run`,
+//! the same lock plan creation uses.
+
+use std::c_str::CString;
+
+use ffi;
+use lock;
+
+/// Export all accumulated wisdom to the file at `path`, creating or
+/// truncating it as needed.
+///
+/// Returns `false` if FFTW was unable to write the file.
+pub fn export_to_file(path: &Path) -> bool {
+    let c_path = path.to_c_str();
+    lock::run(|| unsafe {
+        ffi::fftw_export_wisdom_to_filename(c_path.as_ptr()) != 0
+    })
+}
+
+/// Import wisdom previously written by `export_to_file` (or by any
+/// other FFTW program), merging it with whatever wisdom is already
+/// held.
+///
+/// Returns `false` if the file could not be read, or did not contain
+/// valid wisdom.
+pub fn import_from_file(path: &Path) -> bool {
+    let c_path = path.to_c_str();
+    lock::run(|| unsafe {
+        ffi::fftw_import_wisdom_from_filename(c_path.as_ptr()) != 0
+    })
+}
+
+/// Export all accumulated wisdom to an owned string.
+///
+/// Returns `None` if FFTW failed to produce the export.
+pub fn export_to_string() -> Option<String> {
+    lock::run(|| unsafe {
+        let raw = ffi::fftw_export_wisdom_to_string();
+        if raw.is_null() {
+            return None;
+        }
+        let owned = CString::new(raw as *const i8, false).as_str().map(|s| s.to_string());
+        // FFTW documents this string as allocated with the C library's
+        // plain `malloc`, not `fftw_malloc` (unlike the buffers `mem`
+        // allocates itself), so it must be released with `free`, not
+        // `fftw_free`.
+        ::libc::free(raw as *mut ::libc::c_void);
+        owned
+    })
+}
+
+/// Import wisdom from a string produced by `export_to_string`, merging
+/// it with whatever wisdom is already held.
+///
+/// Returns `false` if `wisdom` did not contain valid wisdom.
+pub fn import_from_string(wisdom: &str) -> bool {
+    let c_wisdom = wisdom.to_c_str();
+    lock::run(|| unsafe {
+        ffi::fftw_import_wisdom_from_string(c_wisdom.as_ptr()) != 0
+    })
+}
+
+/// Discard all accumulated wisdom, returning FFTW to a blank slate.
+pub fn forget() {
+    lock::run(|| unsafe { ffi::fftw_forget_wisdom() })
+}